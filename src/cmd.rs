@@ -1,26 +1,96 @@
 use anyhow::{bail, ensure, Context, Result};
-use bzip2::read::BzDecoder;
+use base64::Engine as _;
 use chrono::Utc;
 use clap::{Parser, ValueHint};
 use indicatif::{MultiProgress, ProgressBar, ProgressFinish, ProgressStyle};
-use lzma::LzmaReader;
 use memmap2::{Mmap, MmapMut};
-use prost::Message;
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::RsaPublicKey;
 use sha2::{Digest, Sha256};
 use sync_unsafe_cell::SyncUnsafeCell;
+use zip::{CompressionMethod, ZipArchive};
 
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fs::{self, File, OpenOptions};
 use std::io::{self, Read};
-use std::ops::{Div, Mul};
+use std::ops::{Deref, Range};
 use std::path::{Path, PathBuf};
-use std::slice;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
 use crate::chromeos_update_engine::install_operation::Type;
-use crate::chromeos_update_engine::{DeltaArchiveManifest, InstallOperation, PartitionUpdate};
+use crate::chromeos_update_engine::PartitionUpdate;
+use crate::extractor::{Extractor, Manifest, PartitionWriter, Progress, ProgressSink};
 use crate::payload::Payload;
+use crate::remote::{self, HttpPayloadSource};
+
+/// The bytes making up `payload.bin`, whether read from a raw payload file or carved out of an
+/// OTA zip without copying it out first.
+struct PayloadFile {
+    mmap: Mmap,
+    range: Range<usize>,
+}
+
+impl Deref for PayloadFile {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.mmap[self.range.clone()]
+    }
+}
+
+/// The outcome of hashing one extracted `.img` file against the digest recorded for it in the
+/// payload manifest.
+struct PartitionVerification {
+    partition: String,
+    expected_hash: String,
+    actual_hash: String,
+    passed: bool,
+}
+
+/// Reports extraction progress through an `indicatif` bar for a single partition.
+struct IndicatifProgress(ProgressBar);
+
+impl ProgressSink for IndicatifProgress {
+    fn on_progress(&self, _partition: &str, progress: Progress) {
+        self.0.set_position(progress.operations_done);
+    }
+}
+
+/// Reports extraction progress for however many partitions are extracted concurrently, creating
+/// one `indicatif` bar per partition the first time it reports progress.
+struct MultiPartitionProgress {
+    multiprogress: MultiProgress,
+    bars: Mutex<HashMap<String, ProgressBar>>,
+}
+
+impl MultiPartitionProgress {
+    fn new() -> Self {
+        Self { multiprogress: MultiProgress::new(), bars: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl ProgressSink for MultiPartitionProgress {
+    fn on_progress(&self, partition: &str, progress: Progress) {
+        let mut bars = self.bars.lock().unwrap();
+        let bar = bars.entry(partition.to_string()).or_insert_with(|| {
+            let bar = ProgressBar::new(progress.operations_total)
+                .with_finish(ProgressFinish::AndLeave)
+                .with_prefix(partition.to_string())
+                .with_style(progress_bar_style().expect("unable to build progress bar template"));
+            self.multiprogress.add(bar)
+        });
+        bar.set_position(progress.operations_done);
+    }
+}
+
+fn progress_bar_style() -> Result<ProgressStyle> {
+    Ok(ProgressStyle::with_template(
+        "{prefix:>16!.green.bold} [{wide_bar:.white.dim}] {percent:>3.white}%",
+    )
+    .context("unable to build progress bar template")?
+    .progress_chars("=> "))
+}
 
 #[derive(Debug, Parser)]
 #[clap(
@@ -48,13 +118,33 @@ pub struct Cmd {
     #[clap(long, value_delimiter = ',', value_name = "PARTITIONS")]
     partitions: Vec<String>,
 
+    /// Directory containing the base build's extracted `.img` files, required to apply
+    /// incremental (delta) OTA payloads
+    #[clap(long, value_hint = ValueHint::DirPath, value_name = "PATH")]
+    source_dir: Option<PathBuf>,
+
     /// Skip input file verification (dangerous!)
     #[clap(long)]
     no_verify: bool,
+
+    /// Check previously extracted `.img` files in `--output-dir` against the payload's partition
+    /// hashes, without re-extracting
+    #[clap(long)]
+    verify_only: bool,
+
+    /// Path to the OTA's RSA public key (PEM-encoded), used to verify the payload's metadata
+    /// signature. Without it, signature verification is skipped with a warning; per-operation and
+    /// whole-partition hash verification still run.
+    #[clap(long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    public_key: Option<PathBuf>,
 }
 
 impl Cmd {
     pub fn run(&self) -> Result<()> {
+        if let Some(url) = self.remote_url() {
+            return self.run_remote(url);
+        }
+
         let payload = self.open_payload_file()?;
         let payload = &Payload::parse(&payload).context("unable to parse payload")?;
         ensure!(
@@ -63,141 +153,316 @@ impl Cmd {
             hex::encode(payload.magic_bytes)
         );
 
-        let manifest =
-            DeltaArchiveManifest::decode(payload.manifest).context("unable to parse manifest")?;
-        let block_size = manifest.block_size.context("block_size not defined")? as usize;
+        let extractor = Extractor::builder()
+            .no_verify(self.no_verify)
+            .concurrency(self.concurrency.unwrap_or(0))
+            .build()?;
+
+        self.verify_metadata_signature_or_warn(&extractor, payload)?;
+
+        let manifest = Manifest::parse(payload)?;
+        let block_size = manifest.block_size()?;
 
         for partition in &self.partitions {
-            if !manifest.partitions.iter().any(|p| &p.partition_name == partition) {
+            if manifest.partition(partition).is_none() {
                 bail!("partition \"{}\" not found in manifest", partition);
             }
         }
 
+        if self.verify_only {
+            let output_dir =
+                self.output_dir.as_deref().context("--verify-only requires --output-dir")?;
+            let results = self.verify_partitions(&manifest, output_dir)?;
+            self.print_verification_summary(&results);
+            ensure!(
+                results.iter().all(|result| result.passed),
+                "one or more partitions failed verification"
+            );
+            return Ok(());
+        }
+
         let partition_dir = self.create_partition_dir()?;
         let partition_dir = partition_dir.as_ref();
+        let multiprogress = MultiProgress::new();
 
-        let threadpool = self.get_threadpool()?;
-        threadpool.scope(|scope| -> Result<()> {
-            let multiprogress = MultiProgress::new();
-            for update in manifest.partitions.iter().filter(|update| {
+        let updates: Vec<&PartitionUpdate> = manifest
+            .partitions()
+            .filter(|update| {
                 self.partitions.is_empty() || self.partitions.contains(&update.partition_name)
-            }) {
-                let progress_bar = self.create_progress_bar(update)?;
-                let progress_bar = multiprogress.add(progress_bar);
-
-                let (partition, partition_len) = self.open_partition_file(update, partition_dir)?;
-                for op in update.operations.iter() {
-                    let progress = progress_bar.clone();
-                    let partition = Arc::clone(&partition);
-
-                    scope.spawn(move |_| {
-                        let partition = unsafe { (*partition.get()).as_mut_ptr() };
-                        self.run_op(op, payload, partition, partition_len as usize, block_size)
-                            .expect("error running operation");
-                        progress.inc(1);
-                    });
-                }
+            })
+            .collect();
+
+        // Open every partition's output and source file up front so they, and their progress
+        // bars, stay alive for the single shared `scope` below: all partitions are spawned into
+        // it and extracted concurrently with each other, not one partition at a time.
+        let mut writers = Vec::with_capacity(updates.len());
+        let mut sources = Vec::with_capacity(updates.len());
+        let mut progresses = Vec::with_capacity(updates.len());
+
+        for &update in &updates {
+            let progress_bar = self.create_progress_bar(update)?;
+            let progress_bar = multiprogress.add(progress_bar);
+
+            let (writer, _) = self.open_partition_file(update, partition_dir)?;
+            let source = self.open_source_partition_file(update)?;
+
+            writers.push(writer);
+            sources.push(source);
+            progresses.push(IndicatifProgress(progress_bar));
+        }
+
+        extractor.scope(|scope| {
+            for (((&update, writer), source), progress) in
+                updates.iter().zip(&writers).zip(&sources).zip(&progresses)
+            {
+                let source = source.as_deref().map(|mmap| &mmap[..]);
+                extractor.extract_partition_in_scope(
+                    scope,
+                    payload,
+                    update,
+                    block_size,
+                    source,
+                    &**writer,
+                    progress,
+                );
             }
             Ok(())
-        })
+        })?;
+
+        if !self.no_verify {
+            let results = self.verify_partitions(&manifest, partition_dir)?;
+            self.print_verification_summary(&results);
+            ensure!(
+                results.iter().all(|result| result.passed),
+                "one or more partitions failed verification"
+            );
+        }
+
+        Ok(())
     }
 
-    fn create_progress_bar(&self, update: &PartitionUpdate) -> Result<ProgressBar> {
-        let finish = ProgressFinish::AndLeave;
-        let style = ProgressStyle::with_template(
-            "{prefix:>16!.green.bold} [{wide_bar:.white.dim}] {percent:>3.white}%",
-        )
-        .context("unable to build progress bar template")?
-        .progress_chars("=> ");
-        let bar = ProgressBar::new(update.operations.len() as u64)
-            .with_finish(finish)
-            .with_prefix(update.partition_name.to_string())
-            .with_style(style);
-        Ok(bar)
+    /// Returns the payload argument as an HTTP(S) URL, if it looks like one rather than a local
+    /// file path.
+    fn remote_url(&self) -> Option<&str> {
+        self.payload
+            .to_str()
+            .filter(|path| path.starts_with("http://") || path.starts_with("https://"))
     }
 
-    fn run_op(
-        &self,
-        op: &InstallOperation,
-        payload: &Payload,
-        partition: *mut u8,
-        partition_len: usize,
-        block_size: usize,
-    ) -> Result<()> {
-        let data_len = op.data_length.context("data_length not defined")? as usize;
-        let mut data = {
-            let offset = op.data_offset.context("data_offset not defined")? as usize;
-            payload
-                .data
-                .get(offset..offset + data_len)
-                .context("data offset exceeds payload size")?
-        };
-        match &op.data_sha256_hash {
-            Some(hash) if !self.no_verify => {
-                self.verify_sha256(data, hash)?;
+    /// Extracts the requested partitions straight from a remotely hosted payload using HTTP
+    /// range requests, downloading only the header, manifest, metadata signature, and the
+    /// operations that make up those partitions.
+    fn run_remote(&self, url: &str) -> Result<()> {
+        ensure!(!self.partitions.is_empty(), "--partitions is required when extracting from a URL");
+        ensure!(self.source_dir.is_none(), "--source-dir is not supported when extracting from a URL");
+        ensure!(!self.verify_only, "--verify-only is not supported when extracting from a URL");
+
+        let source = HttpPayloadSource::new(url)?;
+        let extractor = Extractor::builder()
+            .no_verify(self.no_verify)
+            .concurrency(self.concurrency.unwrap_or(0))
+            .build()?;
+
+        let metadata = remote::fetch_metadata_prefix(&source)?;
+        let payload = &Payload::parse(&metadata).context("unable to parse payload metadata")?;
+        let manifest = Manifest::parse(payload)?;
+
+        for partition in &self.partitions {
+            if manifest.partition(partition).is_none() {
+                bail!("partition \"{}\" not found in manifest", partition);
             }
-            _ => {}
         }
 
-        let mut dst_extents = self
-            .extract_dst_extents(op, partition, partition_len, block_size)
-            .context("error extracting dst_extents")?;
-
-        match Type::from_i32(op.r#type) {
-            Some(Type::Replace) => self
-                .run_op_replace(&mut data, &mut dst_extents, block_size)
-                .context("error in REPLACE operation"),
-            Some(Type::ReplaceBz) => {
-                let mut decoder = BzDecoder::new(data);
-                self.run_op_replace(&mut decoder, &mut dst_extents, block_size)
-                    .context("error in REPLACE_BZ operation")
+        self.verify_metadata_signature_or_warn(&extractor, payload)?;
+
+        let partition_dir = self.create_partition_dir()?;
+        let partition_dir = partition_dir.as_ref().to_path_buf();
+        let progress = MultiPartitionProgress::new();
+
+        remote::extract_partitions(
+            &source,
+            &extractor,
+            &manifest,
+            &self.partitions,
+            None,
+            |name, size| {
+                let filename = Path::new(name).with_extension("img");
+                let path = partition_dir.join(filename);
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                    .with_context(|| format!("unable to open file for writing: {path:?}"))?;
+                file.set_len(size)?;
+                let mmap = unsafe { MmapMut::map_mut(&file) }
+                    .with_context(|| format!("failed to mmap file: {path:?}"))?;
+                Ok(Box::new(SyncUnsafeCell::new(mmap)) as Box<dyn PartitionWriter>)
+            },
+            &progress,
+        )?;
+
+        if !self.no_verify {
+            let results = self.verify_partitions(&manifest, &partition_dir)?;
+            self.print_verification_summary(&results);
+            ensure!(
+                results.iter().all(|result| result.passed),
+                "one or more partitions failed verification"
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Verifies the payload's metadata signature against `--public-key`, or prints a warning and
+    /// skips it if no key was given. Shared between the local and remote extraction paths so both
+    /// get the same signature guarantees (or the same warning when they're skipped).
+    fn verify_metadata_signature_or_warn(&self, extractor: &Extractor, payload: &Payload) -> Result<()> {
+        if self.no_verify {
+            return Ok(());
+        }
+
+        match &self.public_key {
+            Some(path) => {
+                let public_key = self.load_public_key(path)?;
+                extractor
+                    .verify_metadata_signature(payload, &public_key)
+                    .context("payload signature verification failed")
             }
-            Some(Type::ReplaceXz) => {
-                let mut decoder = LzmaReader::new_decompressor(data)
-                    .context("unable to initialize lzma decoder")?;
-                self.run_op_replace(&mut decoder, &mut dst_extents, block_size)
-                    .context("error in REPLACE_XZ operation")
+            None => {
+                eprintln!(
+                    "warning: no --public-key given, skipping payload metadata signature \
+                     verification"
+                );
+                Ok(())
             }
-            Some(Type::Zero) => Ok(()), // This is a no-op since the partition is already zeroed
-            Some(op) => bail!("unimplemented operation: {op:?}"),
-            None => bail!("invalid operation"),
         }
     }
 
-    fn run_op_replace(
-        &self,
-        reader: &mut impl Read,
-        dst_extents: &mut [&mut [u8]],
-        block_size: usize,
-    ) -> Result<()> {
-        let mut bytes_read = 0usize;
-
-        let dst_len = dst_extents.iter().map(|extent| extent.len()).sum::<usize>();
-        let (dst_extents_last, dst_extents) = dst_extents.split_last_mut().unwrap();
-
-        for extent in dst_extents.iter_mut() {
-            reader.read_exact(extent).context("failed to write to buffer")?;
-            bytes_read += extent.len();
-        }
-        bytes_read += self
-            .read_exact_best_effort(reader, dst_extents_last)
-            .context("failed to write to buffer")?;
+    /// Reads and parses a PEM-encoded RSA public key for `--public-key`.
+    fn load_public_key(&self, path: &Path) -> Result<RsaPublicKey> {
+        let pem = fs::read_to_string(path)
+            .with_context(|| format!("unable to read public key: {path:?}"))?;
+        RsaPublicKey::from_public_key_pem(&pem)
+            .with_context(|| format!("unable to parse public key: {path:?}"))
+    }
+
+    fn create_progress_bar(&self, update: &PartitionUpdate) -> Result<ProgressBar> {
+        let bar = ProgressBar::new(update.operations.len() as u64)
+            .with_finish(ProgressFinish::AndLeave)
+            .with_prefix(update.partition_name.to_string())
+            .with_style(progress_bar_style()?);
+        Ok(bar)
+    }
 
-        ensure!(reader.bytes().next().is_none(), "read fewer bytes than expected");
+    fn open_source_partition_file(&self, update: &PartitionUpdate) -> Result<Option<Arc<Mmap>>> {
+        let Some(source_dir) = &self.source_dir else {
+            return Ok(None);
+        };
 
-        // Align number of bytes read to block size. The formula for alignment is:
-        // ((operand + alignment - 1) / alignment) * alignment
-        let bytes_read_aligned = (bytes_read + block_size - 1).div(block_size).mul(block_size);
-        ensure!(bytes_read_aligned == dst_len, "more dst blocks than data, even with padding");
+        // An incremental OTA can mix full REPLACE* partitions (e.g. because the source build was
+        // too old to diff against) with delta ones; only the latter need the base build's .img
+        // around at all, so don't require it for a partition that's fully replaced.
+        if !Self::requires_source(update) {
+            return Ok(None);
+        }
 
-        Ok(())
+        let filename = Path::new(&update.partition_name).with_extension("img");
+        let path = source_dir.join(filename);
+        let file = File::open(&path)
+            .with_context(|| format!("unable to open source partition file: {path:?}"))?;
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("failed to mmap source partition file: {path:?}"))?;
+        Ok(Some(Arc::new(mmap)))
+    }
+
+    /// Whether any of `update`'s operations read from the source partition, i.e. need the base
+    /// build's `.img` under `--source-dir` at all.
+    fn requires_source(update: &PartitionUpdate) -> bool {
+        update.operations.iter().any(|op| {
+            matches!(
+                Type::from_i32(op.r#type),
+                Some(Type::SourceCopy | Type::SourceBsdiff | Type::BrotliBsdiff)
+            )
+        })
     }
 
-    fn open_payload_file(&self) -> Result<Mmap> {
+    fn open_payload_file(&self) -> Result<PayloadFile> {
         let path = &self.payload;
         let file = File::open(path)
             .with_context(|| format!("unable to open file for reading: {path:?}"))?;
-        unsafe { Mmap::map(&file) }.with_context(|| format!("failed to mmap file: {path:?}"))
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("failed to mmap file: {path:?}"))?;
+
+        if mmap.get(..4) == Some(&b"PK\x03\x04"[..]) {
+            return self
+                .open_payload_from_zip(mmap)
+                .with_context(|| format!("unable to read payload.bin from OTA zip: {path:?}"));
+        }
+
+        let range = 0..mmap.len();
+        Ok(PayloadFile { mmap, range })
+    }
+
+    /// Locates the `payload.bin` entry inside an OTA zip without extracting it: since it's
+    /// always stored uncompressed, the mmap can be handed to [`Payload::parse`] as-is, sliced to
+    /// the entry's data range.
+    fn open_payload_from_zip(&self, mmap: Mmap) -> Result<PayloadFile> {
+        let mut archive =
+            ZipArchive::new(io::Cursor::new(&mmap[..])).context("unable to read zip directory")?;
+
+        let (offset, size) = {
+            let entry = archive.by_name("payload.bin").context("payload.bin not found in zip")?;
+            ensure!(
+                entry.compression() == CompressionMethod::Stored,
+                "payload.bin is compressed inside the OTA zip, expected it to be stored"
+            );
+            (entry.data_start(), entry.size())
+        };
+        let range = offset as usize..(offset + size) as usize;
+        ensure!(range.end <= mmap.len(), "payload.bin entry exceeds zip file size");
+
+        if !self.no_verify {
+            if let Ok(mut properties) = archive.by_name("payload_properties.txt") {
+                let mut text = String::new();
+                properties
+                    .read_to_string(&mut text)
+                    .context("unable to read payload_properties.txt")?;
+                self.verify_payload_properties(&text, &mmap[range.clone()])?;
+            }
+        }
+
+        Ok(PayloadFile { mmap, range })
+    }
+
+    /// Cross-checks `payload_properties.txt` (`FILE_HASH`/`FILE_SIZE` lines) against the bytes
+    /// that were carved out of the OTA zip as `payload.bin`.
+    fn verify_payload_properties(&self, properties: &str, payload: &[u8]) -> Result<()> {
+        for line in properties.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            match key {
+                "FILE_SIZE" => {
+                    let expected: usize =
+                        value.trim().parse().context("invalid FILE_SIZE in payload_properties.txt")?;
+                    ensure!(
+                        expected == payload.len(),
+                        "payload size mismatch: expected {expected}, got {}",
+                        payload.len()
+                    );
+                }
+                "FILE_HASH" => {
+                    let expected = base64::engine::general_purpose::STANDARD
+                        .decode(value.trim())
+                        .context("invalid FILE_HASH in payload_properties.txt")?;
+                    ensure!(
+                        Sha256::digest(payload).as_slice() == expected,
+                        "payload hash does not match payload_properties.txt"
+                    );
+                }
+                _ => {}
+            }
+        }
+        Ok(())
     }
 
     fn open_partition_file(
@@ -229,61 +494,59 @@ impl Cmd {
         Ok((partition, partition_len))
     }
 
-    fn extract_dst_extents(
+    /// Hashes each already-extracted partition under `partition_dir` (only those named in
+    /// `--partitions`, when given) and compares it against the digest recorded for it in the
+    /// manifest.
+    fn verify_partitions(
         &self,
-        op: &InstallOperation,
-        partition: *mut u8,
-        partition_len: usize,
-        block_size: usize,
-    ) -> Result<Vec<&'static mut [u8]>> {
-        op.dst_extents
-            .iter()
-            .map(|extent| {
-                let start_block =
-                    extent.start_block.context("start_block not defined in extent")? as usize;
-                let num_blocks =
-                    extent.num_blocks.context("num_blocks not defined in extent")? as usize;
-
-                let partition_offset = start_block * block_size;
-                let extent_len = num_blocks * block_size;
-
-                ensure!(
-                    partition_offset + extent_len <= partition_len,
-                    "extent exceeds partition size"
-                );
-                let extent = unsafe {
-                    slice::from_raw_parts_mut(partition.add(partition_offset), extent_len)
-                };
-
-                Ok(extent)
+        manifest: &Manifest,
+        partition_dir: impl AsRef<Path>,
+    ) -> Result<Vec<PartitionVerification>> {
+        manifest
+            .partitions()
+            .filter(|update| {
+                self.partitions.is_empty() || self.partitions.contains(&update.partition_name)
             })
+            .map(|update| self.verify_partition(update, partition_dir.as_ref()))
             .collect()
     }
 
-    fn verify_sha256(&self, data: &[u8], exp_hash: &[u8]) -> Result<()> {
-        let got_hash = Sha256::digest(data);
-        ensure!(
-            got_hash.as_slice() == exp_hash,
-            "hash mismatch: expected {}, got {got_hash:x}",
-            hex::encode(exp_hash)
-        );
-        Ok(())
+    fn verify_partition(
+        &self,
+        update: &PartitionUpdate,
+        partition_dir: &Path,
+    ) -> Result<PartitionVerification> {
+        let expected = update
+            .new_partition_info
+            .as_ref()
+            .and_then(|info| info.hash.clone())
+            .context("new_partition_info hash not defined")?;
+
+        let filename = Path::new(&update.partition_name).with_extension("img");
+        let path = partition_dir.join(filename);
+        let file = File::open(&path)
+            .with_context(|| format!("unable to open partition file for verification: {path:?}"))?;
+        let mmap = unsafe { Mmap::map(&file) }
+            .with_context(|| format!("failed to mmap file: {path:?}"))?;
+        let actual = Sha256::digest(&mmap[..]);
+
+        Ok(PartitionVerification {
+            partition: update.partition_name.clone(),
+            passed: actual.as_slice() == expected,
+            expected_hash: hex::encode(&expected),
+            actual_hash: hex::encode(actual),
+        })
     }
 
-    /// Read as much as possible from a reader into a buffer.
-    /// This is similar to [`Read::read_exact`], but does not error out when the
-    /// buffer is full.
-    fn read_exact_best_effort(&self, reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
-        let mut bytes_read = 0;
-        while bytes_read < buf.len() {
-            match reader.read(&mut buf[bytes_read..]) {
-                Ok(0) => break,
-                Ok(n) => bytes_read += n,
-                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
-                Err(e) => return Err(e),
-            }
+    fn print_verification_summary(&self, results: &[PartitionVerification]) {
+        println!("{:<24}  {:<6}  {}", "PARTITION", "RESULT", "EXPECTED / ACTUAL SHA-256");
+        for result in results {
+            let status = if result.passed { "OK" } else { "FAILED" };
+            println!(
+                "{:<24}  {:<6}  {} / {}",
+                result.partition, status, result.expected_hash, result.actual_hash
+            );
         }
-        Ok(bytes_read)
     }
 
     fn create_partition_dir(&self) -> Result<Cow<PathBuf>> {
@@ -300,12 +563,4 @@ impl Cmd {
             .with_context(|| format!("could not create output directory: {dir:?}"))?;
         Ok(dir)
     }
-
-    fn get_threadpool(&self) -> Result<ThreadPool> {
-        let concurrency = self.concurrency.unwrap_or(0);
-        ThreadPoolBuilder::new()
-            .num_threads(concurrency)
-            .build()
-            .context("unable to start threadpool")
-    }
 }
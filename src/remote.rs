@@ -0,0 +1,175 @@
+//! Extracts selected partitions directly from a payload hosted on an HTTP(S) server, using byte
+//! range requests so only the header, manifest, metadata signature, and the operations belonging
+//! to the requested partitions are ever downloaded.
+//!
+//! [`PayloadSource`] abstracts "give me `len` bytes starting at `offset`", mirroring the
+//! `BlockIO`-style indirection other dumpers use to support multiple backends; [`HttpPayloadSource`]
+//! is the only implementation provided here, backed by `Range` requests against a plain URL.
+
+use anyhow::{ensure, Context, Result};
+use ureq::Agent;
+
+use std::io::Read;
+
+use crate::chromeos_update_engine::InstallOperation;
+use crate::extractor::{Extractor, Manifest, PartitionWriter, ProgressSink};
+
+const MAGIC: &[u8; 4] = b"CrAU";
+
+/// A source of payload bytes addressable by byte range, so a payload can be read without
+/// holding the whole thing in memory or on disk.
+pub trait PayloadSource {
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>>;
+}
+
+/// Fetches payload bytes over HTTP(S) using `Range` requests.
+pub struct HttpPayloadSource {
+    agent: Agent,
+    url: String,
+}
+
+impl HttpPayloadSource {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let url = url.into();
+        let agent = Agent::new();
+
+        let head = agent
+            .head(&url)
+            .call()
+            .with_context(|| format!("HEAD request failed: {url}"))?;
+        let accepts_ranges =
+            head.header("Accept-Ranges").is_some_and(|value| value.eq_ignore_ascii_case("bytes"));
+        ensure!(
+            accepts_ranges,
+            "server does not advertise \"Accept-Ranges: bytes\"; remote extraction requires range \
+             request support"
+        );
+
+        Ok(Self { agent, url })
+    }
+}
+
+impl PayloadSource for HttpPayloadSource {
+    fn read_at(&self, offset: u64, len: usize) -> Result<Vec<u8>> {
+        // SOURCE_COPY and Zero operations carry no data blob (data_length == 0); issuing a range
+        // request for them would build an inverted `bytes=N-(N-1)` range that no server honors
+        // with 206 (and underflows when offset is also 0), so skip the request entirely.
+        if len == 0 {
+            return Ok(Vec::new());
+        }
+
+        let range = format!("bytes={offset}-{}", offset + len as u64 - 1);
+        let response = self
+            .agent
+            .get(&self.url)
+            .set("Range", &range)
+            .call()
+            .with_context(|| format!("range request failed: {range}"))?;
+
+        // A server that ignores Range and returns the whole body with 200 would otherwise look
+        // like a valid (but truncated) response once `.take(len)` trims it, silently handing back
+        // bytes from the wrong offset.
+        ensure!(
+            response.status() == 206,
+            "server did not honor the range request for {range} (expected HTTP 206, got {})",
+            response.status()
+        );
+
+        let mut buf = Vec::with_capacity(len);
+        response
+            .into_reader()
+            .take(len as u64)
+            .read_to_end(&mut buf)
+            .context("failed to read range response body")?;
+        ensure!(buf.len() == len, "server returned fewer bytes than requested for {range}");
+
+        Ok(buf)
+    }
+}
+
+/// The layout of the fixed-size payload header, as byte offsets into the source.
+struct Header {
+    data_offset: u64,
+}
+
+fn read_header(source: &dyn PayloadSource) -> Result<Header> {
+    // magic(4) + file_format_version(8, big-endian) + manifest_len(8, big-endian), followed by
+    // metadata_signature_len(4, big-endian) when file_format_version >= 2.
+    let prefix = source.read_at(0, 20).context("unable to fetch payload header")?;
+    ensure!(&prefix[0..4] == MAGIC, "invalid magic bytes: {}", hex::encode(&prefix[0..4]));
+
+    let version = u64::from_be_bytes(prefix[4..12].try_into().unwrap());
+    let manifest_len = u64::from_be_bytes(prefix[12..20].try_into().unwrap());
+
+    let (header_len, metadata_signature_len) = if version >= 2 {
+        let suffix = source.read_at(20, 4).context("unable to fetch metadata signature length")?;
+        (24u64, u32::from_be_bytes(suffix.try_into().unwrap()) as u64)
+    } else {
+        (20u64, 0)
+    };
+
+    Ok(Header { data_offset: header_len + manifest_len + metadata_signature_len })
+}
+
+/// Fetches the header, manifest, and metadata signature as one contiguous buffer — everything
+/// before the first operation's data — in the same byte layout `Payload::parse` expects when
+/// reading a local file. The caller parses the payload and manifest from it, and uses it for
+/// `--public-key` metadata signature verification, so a remote extraction gets the same check
+/// as a local one without downloading any operation data.
+pub fn fetch_metadata_prefix(source: &dyn PayloadSource) -> Result<Vec<u8>> {
+    let header = read_header(source)?;
+    source.read_at(0, header.data_offset as usize).context("unable to fetch payload metadata")
+}
+
+/// Extracts the named partitions from `manifest` (already fetched and parsed by the caller),
+/// writing each operation's downloaded data straight into `open_writer`.
+pub fn extract_partitions(
+    source: &dyn PayloadSource,
+    extractor: &Extractor,
+    manifest: &Manifest,
+    partitions: &[String],
+    block_size_override: Option<usize>,
+    mut open_writer: impl FnMut(&str, u64) -> Result<Box<dyn PartitionWriter>>,
+    progress: &dyn ProgressSink,
+) -> Result<()> {
+    let header = read_header(source)?;
+    let block_size = match block_size_override {
+        Some(size) => size,
+        None => manifest.block_size()?,
+    };
+
+    for update in manifest.partitions().filter(|update| partitions.contains(&update.partition_name)) {
+        let partition_len = update
+            .new_partition_info
+            .iter()
+            .flat_map(|info| info.size)
+            .next()
+            .context("unable to determine output partition size")?;
+        let writer = open_writer(&update.partition_name, partition_len)?;
+
+        let total = update.operations.len() as u64;
+        for (done, op) in update.operations.iter().enumerate() {
+            let data = fetch_operation_data(source, &header, op)
+                .with_context(|| format!("unable to fetch operation data for {}", update.partition_name))?;
+            extractor
+                .apply_op(op, &data, writer.as_mut_ptr(), writer.len(), None, block_size)
+                .with_context(|| format!("error applying operation to {}", update.partition_name))?;
+            progress.on_progress(
+                &update.partition_name,
+                crate::extractor::Progress { operations_done: done as u64 + 1, operations_total: total },
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn fetch_operation_data(
+    source: &dyn PayloadSource,
+    header: &Header,
+    op: &InstallOperation,
+) -> Result<Vec<u8>> {
+    let offset = header.data_offset + op.data_offset.context("data_offset not defined")?;
+    let len = op.data_length.context("data_length not defined")? as usize;
+    source.read_at(offset, len)
+}